@@ -1,9 +1,129 @@
 #![allow(unused_variables)]
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::hash::{BuildHasher, Hash, Hasher};
+
+pub mod fnv;
+#[cfg(feature = "serde")]
+mod serde_impls;
+pub mod trie;
+pub use fnv::FnvBuildHasher;
+pub use trie::TrieStringInternal;
 
 /// Reference to an internal string.
-#[derive(Copy, Clone, Debug, PartialEq)]
-pub struct StringId(usize);
+///
+/// Generic over the index width `I` (defaulting to `u32`) so a `StringId` can
+/// be shrunk to `u16` to halve the size of structs (AST nodes, symbol maps, ...)
+/// that carry many of them, instead of always paying for a `usize`. `I` only
+/// needs to convert to and from `usize`; `add` panics if the chosen width
+/// overflows.
+///
+/// With the `serde` feature enabled, a `StringId` serializes as its raw
+/// index; it's only meaningful alongside the [`StringInternal`] it came from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StringId<I = u32>(I);
+
+/// Number of bits used by the bloom filter backing [`CachePolicy`] by default.
+/// 64k bits is cheap to keep resident and gives a low false-positive rate for
+/// the "seen it before" check up to a few thousand distinct strings.
+const DEFAULT_BLOOM_BITS: usize = 1 << 16;
+
+/// Strings longer than this are assumed unlikely to repeat often enough to be
+/// worth a dedup lookup, so they bypass the `strings` map entirely by default.
+const DEFAULT_MAX_STRING_LEN: usize = 64;
+
+/// Controls how [`StringInternal`] trades a small chance of duplicate storage
+/// for bounded memory use on adversarial, mostly-unique input (logs, tokenizer
+/// streams over huge files, etc).
+///
+/// Without a `CachePolicy`, every interned string is deduplicated via the
+/// `strings` map, which grows without bound. With one, a bloom filter is used
+/// to guess whether a string has been seen before: the first time a string is
+/// added it is interned but *not* inserted into the dedup map, so one-hit
+/// wonders never pollute it; only once the filter reports a probable repeat
+/// does the string get inserted for true deduplication.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CachePolicy {
+    /// Maximum number of distinct strings kept in the dedup map. Once
+    /// exceeded, the oldest entry is evicted to make room for the new one.
+    pub max_strings_interned: usize,
+    /// Strings longer than this bypass the dedup map (and the bloom filter)
+    /// entirely and are always re-interned.
+    pub max_string_len: usize,
+    /// Number of bits backing the bloom filter. Bigger means fewer false
+    /// positives (fewer strings wrongly treated as repeats).
+    pub bloom_bits: usize,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        Self {
+            max_strings_interned: 4096,
+            max_string_len: DEFAULT_MAX_STRING_LEN,
+            bloom_bits: DEFAULT_BLOOM_BITS,
+        }
+    }
+}
+
+/// Tiny bloom filter over `u64` hashes, used by [`CachePolicy`] to guess
+/// whether a string has been seen before without paying for a hash-map entry
+/// on every single-use string.
+#[derive(Clone, Debug)]
+struct BloomFilter {
+    bits: Vec<u64>,
+}
+
+impl BloomFilter {
+    fn with_bits(num_bits: usize) -> Self {
+        let words = num_bits.max(64).div_ceil(64);
+        Self {
+            bits: vec![0u64; words],
+        }
+    }
+
+    fn hashes(string: &str) -> (u64, u64) {
+        let mut h1 = DefaultHasher::new();
+        string.hash(&mut h1);
+        let h1 = h1.finish();
+
+        // Mix in a salt so the second hash isn't trivially correlated with the first.
+        let mut h2 = DefaultHasher::new();
+        0x9e3779b97f4a7c15u64.hash(&mut h2);
+        string.hash(&mut h2);
+        let h2 = h2.finish();
+
+        (h1, h2)
+    }
+
+    fn positions(&self, string: &str) -> [usize; 2] {
+        let (h1, h2) = Self::hashes(string);
+        let num_bits = self.bits.len() * 64;
+        [(h1 % num_bits as u64) as usize, (h2 % num_bits as u64) as usize]
+    }
+
+    fn is_set(&self, pos: usize) -> bool {
+        self.bits[pos / 64] & (1 << (pos % 64)) != 0
+    }
+
+    fn set(&mut self, pos: usize) {
+        self.bits[pos / 64] |= 1 << (pos % 64);
+    }
+
+    /// Checks whether `string` was probably seen before, setting its bits
+    /// regardless so the next call can tell. Returns `true` only when every
+    /// bit was already set, i.e. this is probably (not certainly) a repeat.
+    fn check_and_set(&mut self, string: &str) -> bool {
+        let positions = self.positions(string);
+        let probably_seen = positions.iter().all(|&pos| self.is_set(pos));
+        for pos in positions {
+            self.set(pos);
+        }
+        probably_seen
+    }
+}
 
 ///
 /// Super simple string interning so you can have references to a single string
@@ -17,71 +137,195 @@ pub struct StringId(usize);
 /// ```
 ///
 /// It's really fast, and just a good way overall to have references to strings.
-pub struct StringInternal<'a> {
-    strings: HashMap<&'a str, StringId>,
+///
+/// Generic over the `strings` dedup map's hasher `S`. Interner keys are
+/// trusted strings from our own program, so the default is [`FnvBuildHasher`],
+/// a fast non-cryptographic hasher, rather than the standard library's
+/// SipHash. Pick any other `BuildHasher` (FxHash, a custom one, ...) via the
+/// `_and_hasher`/`with_hasher` constructors if you need to.
+///
+/// Also generic over the handed-out [`StringId`]'s index width `I` (see its
+/// docs), defaulting to `u32`.
+pub struct StringInternal<'a, S = FnvBuildHasher, I = u32> {
+    strings: HashMap<&'a str, StringId<I>, S>,
     buffer: String,
     buffers: Vec<String>,
     interned: Vec<&'a str>,
+    cache_policy: Option<CachePolicy>,
+    bloom: Option<BloomFilter>,
+    insertion_order: VecDeque<&'a str>,
 }
 
-impl<'a> StringInternal<'a> {
+impl<'a> StringInternal<'a, FnvBuildHasher, u32> {
     /// Returns a new internal string structure, the first internal buffer
     /// will be initialized with 100 bytes so maybe it's a better idea to use
     /// `with_capacity` if you know that you are gonna use this with lots of strings
     pub fn new() -> Self {
-        Self {
-            strings: HashMap::new(),
-            buffer: String::with_capacity(100),
-            buffers: Vec::new(),
-            interned: Vec::with_capacity(4096),
-        }
+        Self::with_hasher(100, FnvBuildHasher)
     }
 
     /// Creates a new string interning structure, initializes the first string buffer
     /// with the specified bytes.
     pub fn with_capacity(capacity: usize) -> Self {
-        Self {
-            strings: HashMap::new(),
-            buffer: String::with_capacity(capacity),
-            buffers: Vec::new(),
-            interned: Vec::with_capacity(4096),
-        }
+        Self::with_hasher(capacity, FnvBuildHasher)
     }
 
     /// Creates a new string interning structure, initializes the first string buffer
     /// with the specified bytes. Also provides a capacity for the number of strings that
     /// are gonna be interned.
     pub fn with_capacity_for_internals(capacity: usize, capacity_internal_strings: usize) -> Self {
+        Self::with_capacity_for_internals_and_hasher(
+            capacity,
+            capacity_internal_strings,
+            FnvBuildHasher,
+        )
+    }
+
+    /// Creates a new string interning structure governed by a [`CachePolicy`]:
+    /// the dedup map is bounded to `policy.max_strings_interned` entries (oldest
+    /// evicted first) and a bloom filter is used to avoid caching strings that
+    /// are likely only seen once. Use this over `new`/`with_capacity` when most
+    /// of your input is one-hit wonders (log lines, tokenizing huge files, ...)
+    /// and you want bounded memory rather than perfect deduplication.
+    pub fn with_cache_policy(
+        capacity: usize,
+        capacity_internal_strings: usize,
+        policy: CachePolicy,
+    ) -> Self {
+        Self::with_cache_policy_and_hasher(
+            capacity,
+            capacity_internal_strings,
+            policy,
+            FnvBuildHasher,
+        )
+    }
+}
+
+impl<'a> Default for StringInternal<'a, FnvBuildHasher, u32> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, S: BuildHasher + Default, I> StringInternal<'a, S, I> {
+    /// Creates a new string interning structure backed by the given hasher.
+    pub fn with_hasher(capacity: usize, hasher: S) -> Self {
+        Self::with_capacity_for_internals_and_hasher(capacity, 4096, hasher)
+    }
+
+    /// Creates a new string interning structure backed by the given hasher,
+    /// with a capacity for the number of strings that are gonna be interned.
+    pub fn with_capacity_for_internals_and_hasher(
+        capacity: usize,
+        capacity_internal_strings: usize,
+        hasher: S,
+    ) -> Self {
+        Self {
+            strings: HashMap::with_hasher(hasher),
+            buffer: String::with_capacity(capacity),
+            buffers: Vec::new(),
+            interned: Vec::with_capacity(capacity_internal_strings),
+            cache_policy: None,
+            bloom: None,
+            insertion_order: VecDeque::new(),
+        }
+    }
+
+    /// Creates a new string interning structure backed by the given hasher
+    /// and governed by a [`CachePolicy`] (see [`StringInternal::with_cache_policy`]).
+    pub fn with_cache_policy_and_hasher(
+        capacity: usize,
+        capacity_internal_strings: usize,
+        policy: CachePolicy,
+        hasher: S,
+    ) -> Self {
+        let bloom = BloomFilter::with_bits(policy.bloom_bits);
         Self {
-            strings: HashMap::new(),
+            strings: HashMap::with_hasher(hasher),
             buffer: String::with_capacity(capacity),
             buffers: Vec::new(),
             interned: Vec::with_capacity(capacity_internal_strings),
+            cache_policy: Some(policy),
+            bloom: Some(bloom),
+            insertion_order: VecDeque::new(),
         }
     }
+}
 
+impl<'a, S: BuildHasher, I: TryFrom<usize> + Copy> StringInternal<'a, S, I>
+where
+    usize: TryFrom<I>,
+{
     /// Adds a new string, keep in mind that it will be cloned if it's a new string.
-    pub fn add<T: AsRef<str>>(&mut self, string: T) -> StringId {
+    /// ### Panics
+    /// * Panics if the number of interned strings overflows the chosen `StringId` width `I`
+    pub fn add<T: AsRef<str>>(&mut self, string: T) -> StringId<I> {
         let string = string.as_ref();
-        let val = self.strings.get(string);
-        if let Some(val) = val {
-            *val
-        } else {
-            let (s, id) = self.intern(string);
-            self.strings.insert(s, id);
-            id
+        if let Some(val) = self.strings.get(string) {
+            return *val;
         }
+
+        if self.cache_policy.is_some() {
+            return self.add_with_cache_policy(string);
+        }
+
+        let (s, id) = self.intern(string);
+        self.strings.insert(s, id);
+        id
+    }
+
+    fn add_with_cache_policy(&mut self, string: &str) -> StringId<I> {
+        let max_string_len = self.cache_policy.as_ref().unwrap().max_string_len;
+        if string.len() > max_string_len {
+            // Too long to be worth deduplicating; always re-intern.
+            let (_, id) = self.intern(string);
+            return id;
+        }
+
+        let probably_seen_before = self.bloom.as_mut().unwrap().check_and_set(string);
+        let (s, id) = self.intern(string);
+        if probably_seen_before {
+            self.insert_with_eviction(s, id);
+        }
+        id
+    }
+
+    fn insert_with_eviction(&mut self, string: &'a str, id: StringId<I>) {
+        let max_strings_interned = self.cache_policy.as_ref().unwrap().max_strings_interned;
+        while self.strings.len() >= max_strings_interned {
+            match self.insertion_order.pop_front() {
+                Some(oldest) => {
+                    self.strings.remove(oldest);
+                }
+                None => break,
+            }
+        }
+        self.strings.insert(string, id);
+        self.insertion_order.push_back(string);
     }
 
     /// Gets a previously added string
     /// ### Panics
     /// * Panics on invalid string id
-    pub fn get(&self, string_id: StringId) -> &'a str {
-        assert!(string_id.0 <= self.interned.len());
-        self.interned[string_id.0]
+    pub fn get(&self, string_id: StringId<I>) -> &'a str {
+        let index = Self::index_of(string_id);
+        assert!(index < self.interned.len());
+        self.interned[index]
     }
 
-    fn intern<T: AsRef<str>>(&mut self, string: T) -> (&'a str, StringId) {
+    /// Gets a previously added string, or `None` if the id is out of range
+    /// instead of panicking.
+    pub fn try_get(&self, string_id: StringId<I>) -> Option<&'a str> {
+        self.interned.get(Self::index_of(string_id)).copied()
+    }
+
+    fn index_of(string_id: StringId<I>) -> usize {
+        usize::try_from(string_id.0)
+            .ok()
+            .expect("StringId index should always fit in usize")
+    }
+
+    fn intern<T: AsRef<str>>(&mut self, string: T) -> (&'a str, StringId<I>) {
         let string = string.as_ref();
         let len = string.len();
         // We do this because we know that if we add this string to the buffer
@@ -103,7 +347,12 @@ impl<'a> StringInternal<'a> {
         let totally_safe_str_ref: &'a str =
             unsafe { std::mem::transmute(&self.buffer[old_len..new_len]) };
         self.interned.push(totally_safe_str_ref);
-        (totally_safe_str_ref, StringId(self.interned.len() - 1))
+        let index = self.interned.len() - 1;
+        let id = match I::try_from(index) {
+            Ok(i) => StringId(i),
+            Err(_) => panic!("interned string count {index} overflows the chosen StringId width"),
+        };
+        (totally_safe_str_ref, id)
     }
 }
 
@@ -133,10 +382,105 @@ mod tests {
         }
     }
 
+    #[test]
+    fn works_with_a_custom_hasher() {
+        use std::collections::hash_map::RandomState;
+
+        let mut db: StringInternal<RandomState> =
+            StringInternal::with_hasher(100, RandomState::new());
+        let string_id = db.add("hello world!");
+        assert_eq!(string_id, db.add("hello world!"));
+        assert_eq!(db.get(string_id), "hello world!");
+    }
+
     #[test]
     fn example_works() {
         let mut string_internal = StringInternal::new();
         let string_id = string_internal.add("Mr. Smith");
         assert_eq!(string_internal.get(string_id), "Mr. Smith");
     }
+
+    #[test]
+    fn cache_policy_dedupes_from_the_second_repeat_onward() {
+        let mut db = StringInternal::with_cache_policy(100, 16, CachePolicy::default());
+        // First sighting: the bloom filter hasn't seen it yet, so it's interned
+        // without going into the dedup map (a one-hit wonder never pollutes it).
+        let first = db.add("repeated");
+        // Second sighting: the filter now reports a probable repeat, so this
+        // occurrence is the one that actually lands in the dedup map.
+        let second = db.add("repeated");
+        assert_ne!(first, second);
+        // Third sighting: the map already has an entry, so this is a true dedup hit.
+        let third = db.add("repeated");
+        assert_eq!(second, third);
+        assert_eq!(db.get(third), "repeated");
+    }
+
+    #[test]
+    fn cache_policy_bypasses_dedup_for_long_strings() {
+        let policy = CachePolicy {
+            max_string_len: 4,
+            ..CachePolicy::default()
+        };
+        let mut db = StringInternal::with_cache_policy(100, 16, policy);
+        let long = "way too long";
+        let first = db.add(long);
+        let second = db.add(long);
+        assert_ne!(first, second);
+        assert_eq!(db.get(first), long);
+        assert_eq!(db.get(second), long);
+    }
+
+    #[test]
+    fn cache_policy_evicts_oldest_once_over_capacity() {
+        let policy = CachePolicy {
+            max_strings_interned: 2,
+            ..CachePolicy::default()
+        };
+        let mut db = StringInternal::with_cache_policy(100, 16, policy);
+        // Seed the bloom filter and the dedup map with three distinct strings.
+        for s in ["a", "b", "c"] {
+            db.add(s);
+            db.add(s);
+        }
+        assert!(db.strings.len() <= 2);
+        // "a" went into the map first, so it's the one evicted for "c".
+        assert!(!db.strings.contains_key("a"));
+        assert!(db.strings.contains_key("c"));
+    }
+
+    #[test]
+    fn supports_narrower_string_id_widths() {
+        let mut db: StringInternal<FnvBuildHasher, u8> =
+            StringInternal::with_capacity_for_internals_and_hasher(100, 4, FnvBuildHasher);
+        let a = db.add("a");
+        let b = db.add("b");
+        assert_ne!(a, b);
+        assert_eq!(db.get(a), "a");
+    }
+
+    #[test]
+    #[should_panic(expected = "overflows the chosen StringId width")]
+    fn panics_when_string_id_width_overflows() {
+        let mut db: StringInternal<FnvBuildHasher, u8> =
+            StringInternal::with_capacity_for_internals_and_hasher(4096, 300, FnvBuildHasher);
+        for i in 0..300 {
+            db.add(i.to_string());
+        }
+    }
+
+    #[test]
+    fn try_get_does_not_panic_on_an_out_of_range_id() {
+        let db = StringInternal::new();
+        assert_eq!(db.try_get(StringId(0)), None);
+    }
+
+    #[test]
+    fn string_id_can_be_used_as_a_hashmap_key() {
+        let mut db = StringInternal::new();
+        let id = db.add("key");
+        let mut map = HashMap::new();
+        map.insert(id, "value");
+        assert_eq!(map.get(&id), Some(&"value"));
+    }
 }
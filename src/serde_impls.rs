@@ -0,0 +1,126 @@
+//! `serde` support for [`StringInternal`], gated behind the `serde` feature.
+//!
+//! Expects the crate's `Cargo.toml` to declare:
+//! ```toml
+//! [dependencies]
+//! serde = { version = "1", features = ["derive"], optional = true }
+//!
+//! [features]
+//! serde = ["dep:serde"]
+//! ```
+
+use crate::{CachePolicy, StringInternal};
+use serde::de::Deserialize;
+use serde::ser::Serialize;
+use std::hash::BuildHasher;
+
+/// Wire format for a [`StringInternal`]: the ordered list of interned strings
+/// (the `interned` order *is* the `StringId` assignment order, so that's all
+/// that's needed to let every `StringId` handed out before a save survive a
+/// save/load cycle) plus the [`CachePolicy`], if any, so a bounded-memory
+/// interner doesn't silently become unbounded across a round trip.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerializedStringInternal {
+    strings: Vec<String>,
+    cache_policy: Option<CachePolicy>,
+}
+
+impl<'a, S, I> Serialize for StringInternal<'a, S, I> {
+    fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+    where
+        Ser: serde::Serializer,
+    {
+        SerializedStringInternal {
+            strings: self.interned.iter().map(|s| (*s).to_owned()).collect(),
+            cache_policy: self.cache_policy.clone(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Rebuilds `buffer`/`buffers`, the `interned` slice references and the
+/// `strings` dedup map by re-interning each string in order (so that index
+/// *i* again maps to `StringId(i)`), restoring the original [`CachePolicy`]
+/// (and a fresh bloom filter sized for it) if one was set. With a policy, the
+/// rebuilt dedup map is held to its `max_strings_interned`, oldest evicted first.
+impl<'de, 'a, S: BuildHasher + Default, I: TryFrom<usize> + Copy> Deserialize<'de>
+    for StringInternal<'a, S, I>
+where
+    usize: TryFrom<I>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let SerializedStringInternal {
+            strings,
+            cache_policy,
+        } = SerializedStringInternal::deserialize(deserializer)?;
+        let total_bytes: usize = strings.iter().map(String::len).sum();
+        let mut interner = match cache_policy {
+            Some(policy) => StringInternal::with_cache_policy_and_hasher(
+                total_bytes.max(1),
+                strings.len(),
+                policy,
+                S::default(),
+            ),
+            None => StringInternal::with_capacity_for_internals_and_hasher(
+                total_bytes.max(1),
+                strings.len(),
+                S::default(),
+            ),
+        };
+        for string in strings {
+            let (interned, id) = interner.intern(string);
+            if interner.cache_policy.is_none() {
+                interner.strings.insert(interned, id);
+            } else if let Some(existing) = interner.strings.get_mut(interned) {
+                *existing = id;
+            } else {
+                interner.insert_with_eviction(interned, id);
+            }
+        }
+        Ok(interner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CachePolicy, StringInternal};
+
+    #[test]
+    fn round_trips_through_json() {
+        let mut original = StringInternal::new();
+        let hello = original.add("hello");
+        let world = original.add("world");
+
+        let json = serde_json::to_string(&original).unwrap();
+        let restored: StringInternal = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(restored.get(hello), "hello");
+        assert_eq!(restored.get(world), "world");
+    }
+
+    #[test]
+    fn cache_policy_survives_a_round_trip() {
+        let policy = CachePolicy {
+            max_strings_interned: 2,
+            max_string_len: 64,
+            bloom_bits: 1024,
+        };
+        let mut original = StringInternal::with_cache_policy(100, 16, policy);
+        let hello = original.add("hello");
+
+        let json = serde_json::to_string(&original).unwrap();
+        let mut restored: StringInternal = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(restored.get(hello), "hello");
+        // The cache policy wasn't lost; its bloom filter is rebuilt empty.
+        assert_eq!(
+            restored.cache_policy.as_ref().map(|p| p.max_strings_interned),
+            Some(2)
+        );
+        // Reloaded strings go back into the (bounded) dedup map.
+        assert_eq!(restored.add("hello"), hello);
+    }
+}
@@ -0,0 +1,179 @@
+use crate::StringId;
+
+/// A single node of the byte-trie backing [`TrieStringInternal`].
+///
+/// `children` is a small linear list rather than a full 256-entry array,
+/// since in practice most nodes only ever branch a handful of ways.
+struct TrieNode<I> {
+    children: Vec<(u8, usize)>,
+    terminal: Option<StringId<I>>,
+    parent: Option<usize>,
+    byte: u8,
+}
+
+///
+/// Alternative interner backend for strings that share a lot of common
+/// prefixes (identifiers like `get_foo`/`get_bar`, path segments, ...).
+/// Instead of a `HashMap<&str, StringId>`, strings are stored as paths
+/// through a byte-trie, so overlapping prefixes only cost one shared chain
+/// of nodes instead of being duplicated per string.
+///
+/// ## Example
+/// ```rs
+/// let mut trie = TrieStringInternal::new();
+/// let string_id = trie.add("get_foo");
+/// assert_eq!(trie.get(string_id), "get_foo");
+/// ```
+///
+/// The tradeoff versus [`crate::StringInternal`]: `add` and `get` walk the
+/// trie byte-by-byte, so single lookups are slower than a hash-map lookup.
+/// In exchange, N strings with heavily overlapping prefixes cost proportional
+/// to the trie's edge count rather than their total byte length, and there's
+/// no separate dedup map. It does not allocate less than `StringInternal`'s
+/// shared buffers, though: every node with children owns its own heap-allocated
+/// child list, and `get` allocates a new `String` on each call.
+///
+/// Generic over the handed-out [`StringId`]'s index width `I`, defaulting to
+/// `u32` like [`crate::StringInternal`].
+pub struct TrieStringInternal<I = u32> {
+    nodes: Vec<TrieNode<I>>,
+    terminals: Vec<usize>,
+}
+
+impl TrieStringInternal<u32> {
+    /// Returns a new, empty trie-backed interner with just its root node.
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![TrieNode {
+                children: Vec::new(),
+                terminal: None,
+                parent: None,
+                byte: 0,
+            }],
+            terminals: Vec::new(),
+        }
+    }
+}
+
+impl<I: TryFrom<usize> + Copy> TrieStringInternal<I>
+where
+    usize: TryFrom<I>,
+{
+    /// Adds a new string, walking (and growing, where needed) the trie one
+    /// byte at a time. Equal strings always walk to the same terminal node,
+    /// so equal `StringId`s still guarantee equal strings.
+    /// ### Panics
+    /// * Panics if the number of interned strings overflows the chosen `StringId` width `I`
+    pub fn add<T: AsRef<str>>(&mut self, string: T) -> StringId<I> {
+        let string = string.as_ref();
+        let mut node = 0;
+        for &byte in string.as_bytes() {
+            node = self.child_or_insert(node, byte);
+        }
+        if let Some(id) = self.nodes[node].terminal {
+            return id;
+        }
+        let index = self.terminals.len();
+        let id = match I::try_from(index) {
+            Ok(i) => StringId(i),
+            Err(_) => panic!("interned string count {index} overflows the chosen StringId width"),
+        };
+        self.terminals.push(node);
+        self.nodes[node].terminal = Some(id);
+        id
+    }
+
+    /// Gets a previously added string by walking parent pointers from its
+    /// terminal node back up to the root and reversing the collected bytes.
+    /// Unlike `StringInternal::get`, this has to allocate a new `String`
+    /// since the trie only stores the bytes as shared edges, not a
+    /// contiguous buffer.
+    /// ### Panics
+    /// * Panics on invalid string id
+    pub fn get(&self, string_id: StringId<I>) -> String {
+        self.reconstruct(self.terminals[Self::index_of(string_id)])
+    }
+
+    /// Gets a previously added string, or `None` if the id is out of range
+    /// instead of panicking.
+    pub fn try_get(&self, string_id: StringId<I>) -> Option<String> {
+        self.terminals
+            .get(Self::index_of(string_id))
+            .map(|&node| self.reconstruct(node))
+    }
+
+    fn index_of(string_id: StringId<I>) -> usize {
+        usize::try_from(string_id.0)
+            .ok()
+            .expect("StringId index should always fit in usize")
+    }
+
+    fn reconstruct(&self, mut node: usize) -> String {
+        let mut bytes = Vec::new();
+        while let Some(parent) = self.nodes[node].parent {
+            bytes.push(self.nodes[node].byte);
+            node = parent;
+        }
+        bytes.reverse();
+        String::from_utf8(bytes).expect("trie only ever stores bytes pushed from valid &str input")
+    }
+
+    fn child_or_insert(&mut self, node: usize, byte: u8) -> usize {
+        if let Some(&(_, child)) = self.nodes[node].children.iter().find(|(b, _)| *b == byte) {
+            return child;
+        }
+        let child = self.nodes.len();
+        self.nodes.push(TrieNode {
+            children: Vec::new(),
+            terminal: None,
+            parent: Some(node),
+            byte,
+        });
+        self.nodes[node].children.push((byte, child));
+        child
+    }
+}
+
+impl Default for TrieStringInternal<u32> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trie::TrieStringInternal;
+
+    #[test]
+    fn it_works_basic() {
+        let mut trie = TrieStringInternal::new();
+        let string_id = trie.add("hello world!");
+        assert_eq!(string_id, trie.add("hello world!"));
+        assert_eq!(trie.get(string_id), "hello world!");
+    }
+
+    #[test]
+    fn shares_prefixes_between_strings() {
+        let mut trie = TrieStringInternal::new();
+        let foo = trie.add("get_foo");
+        let bar = trie.add("get_bar");
+        assert_ne!(foo, bar);
+        assert_eq!(trie.get(foo), "get_foo");
+        assert_eq!(trie.get(bar), "get_bar");
+    }
+
+    #[test]
+    fn example_works() {
+        let mut trie = TrieStringInternal::new();
+        let string_id = trie.add("Mr. Smith");
+        assert_eq!(trie.get(string_id), "Mr. Smith");
+    }
+
+    #[test]
+    fn try_get_does_not_panic_on_an_out_of_range_id() {
+        use crate::StringId;
+
+        let trie = TrieStringInternal::new();
+        assert_eq!(trie.try_get(StringId(0)), None);
+    }
+}